@@ -1,12 +1,15 @@
 use std::ops::Mul;
 
 use anchor_lang::{prelude::*, solana_program::native_token::LAMPORTS_PER_SOL};
+use anchor_spl::token::{Mint, ID as TOKEN_PROGRAM_ID};
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 use rust_decimal::prelude::*;
 
 use switchboard_on_demand::on_demand::accounts::pull_feed::PullFeedAccountData;
 declare_id!("BqeULWKoq51Ts7uoiqk1Sgc3PXjiHrMTvpfzPRh5aeLr");
 pub const MAXIMUM_AGE: u64 = 30; // 30 seconds
+pub const BPS_SCALE: u64 = 10_000; // 100.00%
+pub const DEFAULT_EMA_HALF_LIFE: u64 = 60; // 60 seconds
 
 #[program]
 pub mod oracle_priority {
@@ -17,14 +20,54 @@ pub mod oracle_priority {
         let oracle_info = &mut ctx.accounts.oracle_info;
 
         // Initialize with default settings
+        oracle_info.authority = ctx.accounts.payer.key();
         oracle_info.vault_type = ctx.accounts.vault_type.key();
         oracle_info.oracle_pyth = [0u8; 32];
         oracle_info.oracle_switchboard = Pubkey::default();
         oracle_info.priority_pyth = -1; // Disabled by default
         oracle_info.priority_switchboard = -1; // Disabled by default
+        oracle_info.oracle_amm = Pubkey::default();
+        oracle_info.priority_amm = -1; // Disabled by default
         oracle_info.vault_type_name = vault_type_name;
         oracle_info.recent_price = 0;
         oracle_info.last_update = 0;
+        oracle_info.max_confidence_bps = BPS_SCALE as u16; // by default rejects only ratios above 100%
+        oracle_info.max_deviation_bps = BPS_SCALE as u16; // by default rejects only deviations above 100%
+        oracle_info.ema_price = 0;
+        oracle_info.ema_half_life = DEFAULT_EMA_HALF_LIFE;
+
+        Ok(())
+    }
+
+    pub fn update_ema_half_life(
+        ctx: Context<UpdateEmaHalfLife>,
+        ema_half_life: u64,
+    ) -> Result<()> {
+        let oracle_info = &mut ctx.accounts.oracle_info;
+
+        oracle_info.ema_half_life = ema_half_life;
+
+        Ok(())
+    }
+
+    pub fn update_confidence(
+        ctx: Context<UpdateConfidence>,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        let oracle_info = &mut ctx.accounts.oracle_info;
+
+        oracle_info.max_confidence_bps = max_confidence_bps;
+
+        Ok(())
+    }
+
+    pub fn update_deviation(
+        ctx: Context<UpdateDeviation>,
+        max_deviation_bps: u16,
+    ) -> Result<()> {
+        let oracle_info = &mut ctx.accounts.oracle_info;
+
+        oracle_info.max_deviation_bps = max_deviation_bps;
 
         Ok(())
     }
@@ -33,17 +76,19 @@ pub mod oracle_priority {
         ctx: Context<UpdatePriority>,
         pyth_priority: i8,
         switchboard_priority: i8,
+        amm_priority: i8,
     ) -> Result<()> {
         let oracle_info = &mut ctx.accounts.oracle_info;
 
         // Validate priorities
         require!(
-            check_oracle_priorities(pyth_priority, switchboard_priority),
+            check_oracle_priorities(pyth_priority, switchboard_priority, amm_priority),
             OracleError::InvalidPriorities
         );
 
         oracle_info.priority_pyth = pyth_priority;
         oracle_info.priority_switchboard = switchboard_priority;
+        oracle_info.priority_amm = amm_priority;
 
         Ok(())
     }
@@ -52,11 +97,13 @@ pub mod oracle_priority {
         ctx: Context<UpdateOracles>,
         pyth_oracle: [u8; 32],
         switchboard_oracle: Pubkey,
+        amm_oracle: Pubkey,
     ) -> Result<()> {
         let oracle_info = &mut ctx.accounts.oracle_info;
 
         oracle_info.oracle_pyth = pyth_oracle;
         oracle_info.oracle_switchboard = switchboard_oracle;
+        oracle_info.oracle_amm = amm_oracle;
 
         Ok(())
     }
@@ -65,33 +112,90 @@ pub mod oracle_priority {
         let oracle_info = &mut ctx.accounts.oracle_info;
         let clock = Clock::get()?;
 
-        let mut prices: [Option<Decimal>; 2] = [None, None];
+        let mut prices: [Option<Decimal>; 3] = [None, None, None];
+        let mut sources: [Option<PriceSource>; 3] = [None, None, None];
+        let mut pyth_price: Option<Decimal> = None;
+        let mut switchboard_price: Option<Decimal> = None;
 
         // Get Pyth price if enabled
         if oracle_info.priority_pyth >= 0 {
-            if let Ok(p) = load_pyth(&ctx.accounts.pyth_price_info, oracle_info.oracle_pyth) {
+            if let Ok(p) = load_pyth(
+                &ctx.accounts.pyth_price_info,
+                oracle_info.oracle_pyth,
+                oracle_info.max_confidence_bps,
+            ) {
                 msg!(
                     "Assigning pyth price to index {}",
                     oracle_info.priority_pyth
                 );
-                prices[oracle_info.priority_pyth as usize] = Some(p)
+                prices[oracle_info.priority_pyth as usize] = Some(p);
+                sources[oracle_info.priority_pyth as usize] = Some(PriceSource::Pyth);
+                pyth_price = Some(p);
             }
         }
 
         // Get Switchboard price if enabled
         if oracle_info.priority_switchboard >= 0 {
-            if let Ok(p) = load_switchboard(&ctx.accounts.switchboard_feed_info) {
+            if let Ok(p) = load_switchboard(
+                &ctx.accounts.switchboard_feed_info,
+                oracle_info.max_confidence_bps,
+                &clock,
+            ) {
                 msg!(
                     "Assigning switchboard price to index {}",
                     oracle_info.priority_switchboard
                 );
-                prices[oracle_info.priority_switchboard as usize] = Some(p)
+                prices[oracle_info.priority_switchboard as usize] = Some(p);
+                sources[oracle_info.priority_switchboard as usize] = Some(PriceSource::Switchboard);
+                switchboard_price = Some(p);
             }
         }
+
+        // Get AMM pool price if enabled
+        if oracle_info.priority_amm >= 0 {
+            if let Ok(p) = load_amm(
+                &ctx.accounts.amm_pool_info,
+                &ctx.accounts.amm_mint_a,
+                &ctx.accounts.amm_mint_b,
+            ) {
+                msg!("Assigning amm price to index {}", oracle_info.priority_amm);
+                prices[oracle_info.priority_amm as usize] = Some(p);
+                sources[oracle_info.priority_amm as usize] = Some(PriceSource::Amm);
+            }
+        }
+
+        // When both oracles are live, guard against them disagreeing by more
+        // than the configured tolerance rather than silently trusting priority.
+        if let (Some(a), Some(b)) = (pyth_price, switchboard_price) {
+            require!(
+                deviation_bps(a, b) <= oracle_info.max_deviation_bps as u64,
+                OracleError::OracleDivergence
+            );
+        }
+
         // Use first available price based on priority
-        if let Some(price) = prices.iter().flatten().next() {
+        if let Some(index) = prices.iter().position(Option::is_some) {
+            let price = prices[index].unwrap();
             oracle_info.recent_price = price.to_account();
+            oracle_info.ema_price = update_ema(
+                oracle_info.ema_price,
+                oracle_info.recent_price,
+                oracle_info.last_update,
+                clock.unix_timestamp as u64,
+                oracle_info.ema_half_life,
+            );
             oracle_info.last_update = clock.unix_timestamp as u64;
+
+            emit!(PriceUpdateLog {
+                vault_type: oracle_info.vault_type,
+                price_wad: oracle_info.recent_price,
+                source: sources[index].unwrap(),
+                slot: clock.slot,
+                unix_timestamp: clock.unix_timestamp,
+                pyth_available: pyth_price.is_some(),
+                switchboard_available: switchboard_price.is_some(),
+            });
+
             Ok(())
         } else {
             Err(OracleError::NoPriceAvailable.into())
@@ -107,7 +211,7 @@ pub struct Initialize<'info> {
     #[account(
         init,
         payer = payer,
-        space = 8 + 32 + 32 + 32 + 2 + 32 + 8 + 8 + 8,
+        space = 8 + 32 + 32 + 32 + 32 + 2 + 32 + 8 + 8 + 2 + 2 + 8 + 32 + 1 + 16 + 8,
         seeds = [vault_type.key().as_ref(), b"Oracle"],
         bump
     )]
@@ -126,6 +230,49 @@ pub struct UpdatePriority<'info> {
 
     #[account(
         mut,
+        has_one = authority,
+        seeds = [oracle_info.vault_type.as_ref(), b"Oracle"],
+        bump
+    )]
+    pub oracle_info: Account<'info, OracleInfo>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfidence<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [oracle_info.vault_type.as_ref(), b"Oracle"],
+        bump
+    )]
+    pub oracle_info: Account<'info, OracleInfo>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateDeviation<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [oracle_info.vault_type.as_ref(), b"Oracle"],
+        bump
+    )]
+    pub oracle_info: Account<'info, OracleInfo>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEmaHalfLife<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority,
         seeds = [oracle_info.vault_type.as_ref(), b"Oracle"],
         bump
     )]
@@ -139,6 +286,7 @@ pub struct UpdateOracles<'info> {
 
     #[account(
         mut,
+        has_one = authority,
         seeds = [oracle_info.vault_type.as_ref(), b"Oracle"],
         bump
     )]
@@ -158,18 +306,54 @@ pub struct GetPrice<'info> {
 
     /// CHECK: Validated in program logic
     pub switchboard_feed_info: AccountInfo<'info>,
+
+    /// CHECK: owner, discriminator and mint match are validated in load_amm
+    pub amm_pool_info: AccountInfo<'info>,
+
+    /// CHECK: only read when priority_amm >= 0; owner and mint match against
+    /// the pool are validated in load_amm
+    pub amm_mint_a: AccountInfo<'info>,
+
+    /// CHECK: only read when priority_amm >= 0; owner and mint match against
+    /// the pool are validated in load_amm
+    pub amm_mint_b: AccountInfo<'info>,
 }
 
 #[account]
 pub struct OracleInfo {
+    pub authority: Pubkey,
     pub vault_type: Pubkey,
     pub oracle_pyth: [u8; 32],
     pub oracle_switchboard: Pubkey,
     pub priority_pyth: i8,
     pub priority_switchboard: i8,
+    pub oracle_amm: Pubkey,
+    pub priority_amm: i8,
     pub vault_type_name: String,
     pub recent_price: u128,
     pub last_update: u64,
+    pub max_confidence_bps: u16,
+    pub max_deviation_bps: u16,
+    pub ema_price: u128,
+    pub ema_half_life: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceSource {
+    Pyth,
+    Switchboard,
+    Amm,
+}
+
+#[event]
+pub struct PriceUpdateLog {
+    pub vault_type: Pubkey,
+    pub price_wad: u128,
+    pub source: PriceSource,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    pub pyth_available: bool,
+    pub switchboard_available: bool,
 }
 
 #[error_code]
@@ -178,16 +362,26 @@ pub enum OracleError {
     InvalidPriorities,
     #[msg("No price available from configured oracles")]
     NoPriceAvailable,
+    #[msg("Oracle price confidence interval is too wide")]
+    ConfidenceTooWide,
+    #[msg("Switchboard price is older than the maximum allowed age")]
+    StaleSwitchboardPrice,
+    #[msg("Switchboard feed account is malformed or has no value")]
+    InvalidSwitchboardFeed,
+    #[msg("Pyth and Switchboard prices diverge by more than the allowed tolerance")]
+    OracleDivergence,
+    #[msg("AMM pool account failed owner, discriminator, or mint validation")]
+    InvalidAmmPool,
 }
 
-fn check_oracle_priorities(pyth: i8, switchboard: i8) -> bool {
+fn check_oracle_priorities(pyth: i8, switchboard: i8, amm: i8) -> bool {
     // At least one oracle must be enabled
-    if pyth < 0 && switchboard < 0 {
+    if pyth < 0 && switchboard < 0 && amm < 0 {
         return false;
     }
 
     // Validate priority ranges
-    if (pyth >= 0 && pyth > 2) || (switchboard >= 0 && switchboard > 2) {
+    if (pyth >= 0 && pyth > 2) || (switchboard >= 0 && switchboard > 2) || (amm >= 0 && amm > 2) {
         return false;
     }
 
@@ -195,30 +389,239 @@ fn check_oracle_priorities(pyth: i8, switchboard: i8) -> bool {
     if pyth >= 0 && switchboard >= 0 && pyth == switchboard {
         return false;
     }
+    if pyth >= 0 && amm >= 0 && pyth == amm {
+        return false;
+    }
+    if switchboard >= 0 && amm >= 0 && switchboard == amm {
+        return false;
+    }
 
     true
 }
 
-fn load_switchboard<'a>(oracle_switchboard: &AccountInfo<'a>) -> Result<Decimal> {
+fn load_switchboard<'a>(
+    oracle_switchboard: &AccountInfo<'a>,
+    max_confidence_bps: u16,
+    clock: &Clock,
+) -> Result<Decimal> {
     let feed_account = oracle_switchboard.data.borrow();
-    let feed = PullFeedAccountData::parse(feed_account).unwrap();
+    let feed = PullFeedAccountData::parse(feed_account)
+        .map_err(|_| OracleError::InvalidSwitchboardFeed)?;
 
     msg!("Switchboard unpack start");
 
-    let price = feed.value().unwrap();
+    let last_update_timestamp = feed
+        .last_update_timestamp()
+        .ok_or(OracleError::StaleSwitchboardPrice)?;
+    let price_age = (clock.unix_timestamp - last_update_timestamp).max(0) as u64;
+    require!(
+        price_age <= MAXIMUM_AGE,
+        OracleError::StaleSwitchboardPrice
+    );
+
+    let price = feed
+        .value()
+        .ok_or(OracleError::InvalidSwitchboardFeed)?;
+    let range = feed
+        .range()
+        .ok_or(OracleError::InvalidSwitchboardFeed)?;
+
+    require!(
+        confidence_ratio_bps(range, price) <= max_confidence_bps as u64,
+        OracleError::ConfidenceTooWide
+    );
 
     Ok(price)
 }
 
-fn load_pyth<'a>(oracle_pyth: &Account<'a, PriceUpdateV2>, feed_id: [u8; 32]) -> Result<Decimal> {
+fn load_pyth<'a>(
+    oracle_pyth: &Account<'a, PriceUpdateV2>,
+    feed_id: [u8; 32],
+    max_confidence_bps: u16,
+) -> Result<Decimal> {
     let current_timestamp = Clock::get()?;
     let price = oracle_pyth.get_price_no_older_than(&current_timestamp, MAXIMUM_AGE, &feed_id)?;
     msg!("Pyth price was: {:?}", price);
     let p = Decimal::from_i128_with_scale(price.price as i128, price.exponent.mul(-1) as u32);
+    let conf = Decimal::from_i128_with_scale(price.conf as i128, price.exponent.mul(-1) as u32);
     msg!("Pyth decimal was: {:?}", p);
+
+    require!(
+        confidence_ratio_bps(conf, p) <= max_confidence_bps as u64,
+        OracleError::ConfidenceTooWide
+    );
+
     Ok(p)
 }
 
+// Orca Whirlpool program id (mainnet): whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc
+const ORCA_WHIRLPOOL_PROGRAM_ID: Pubkey =
+    pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+// Raydium CLMM program id (mainnet)
+const RAYDIUM_CLMM_PROGRAM_ID: Pubkey = pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+
+// Anchor account discriminators, sha256("account:<StructName>")[..8].
+const ORCA_WHIRLPOOL_DISCRIMINATOR: [u8; 8] = [63, 149, 209, 12, 225, 128, 99, 9];
+const RAYDIUM_POOL_STATE_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+
+// Byte offsets (from the start of the account data, 8-byte discriminator
+// included) of the fields we need out of each
+// program's pool account. Orca's `Whirlpool` and Raydium's `PoolState` lay
+// their header fields out differently, so sqrt_price/mint offsets differ too.
+struct AmmPoolLayout {
+    sqrt_price_offset: usize,
+    mint_a_offset: usize,
+    mint_b_offset: usize,
+}
+
+const ORCA_WHIRLPOOL_LAYOUT: AmmPoolLayout = AmmPoolLayout {
+    sqrt_price_offset: 65,
+    mint_a_offset: 101,
+    mint_b_offset: 181,
+};
+
+const RAYDIUM_POOL_STATE_LAYOUT: AmmPoolLayout = AmmPoolLayout {
+    sqrt_price_offset: 253,
+    mint_a_offset: 73,
+    mint_b_offset: 105,
+};
+
+fn read_pubkey_at(data: &[u8], offset: usize) -> Result<Pubkey> {
+    require!(data.len() >= offset + 32, OracleError::InvalidAmmPool);
+    let bytes: [u8; 32] = data[offset..offset + 32].try_into().unwrap();
+    Ok(Pubkey::from(bytes))
+}
+
+fn read_u128_at(data: &[u8], offset: usize) -> Result<u128> {
+    require!(data.len() >= offset + 16, OracleError::InvalidAmmPool);
+    let bytes: [u8; 16] = data[offset..offset + 16].try_into().unwrap();
+    Ok(u128::from_le_bytes(bytes))
+}
+
+// Deserializes and validates an AccountInfo as an SPL mint owned by the
+// token program. `amm_mint_a`/`amm_mint_b` are untyped `AccountInfo`s (rather
+// than `Account<Mint>`) so that get_price doesn't force every vault to
+// supply real mints just to parse accounts when AMM pricing is disabled.
+fn load_mint<'a>(mint_info: &AccountInfo<'a>) -> Result<Mint> {
+    require!(
+        mint_info.owner == &TOKEN_PROGRAM_ID,
+        OracleError::InvalidAmmPool
+    );
+    let data = mint_info.data.borrow();
+    Mint::try_deserialize(&mut data.as_ref()).map_err(|_| OracleError::InvalidAmmPool.into())
+}
+
+fn load_amm<'a>(
+    amm_pool: &AccountInfo<'a>,
+    amm_mint_a: &AccountInfo<'a>,
+    amm_mint_b: &AccountInfo<'a>,
+) -> Result<Decimal> {
+    require!(
+        amm_pool.data.borrow().len() >= 8,
+        OracleError::InvalidAmmPool
+    );
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&amm_pool.data.borrow()[..8]);
+
+    let layout = if amm_pool.owner == &ORCA_WHIRLPOOL_PROGRAM_ID {
+        require!(
+            discriminator == ORCA_WHIRLPOOL_DISCRIMINATOR,
+            OracleError::InvalidAmmPool
+        );
+        &ORCA_WHIRLPOOL_LAYOUT
+    } else if amm_pool.owner == &RAYDIUM_CLMM_PROGRAM_ID {
+        require!(
+            discriminator == RAYDIUM_POOL_STATE_DISCRIMINATOR,
+            OracleError::InvalidAmmPool
+        );
+        &RAYDIUM_POOL_STATE_LAYOUT
+    } else {
+        return Err(OracleError::InvalidAmmPool.into());
+    };
+
+    let mint_a = load_mint(amm_mint_a)?;
+    let mint_b = load_mint(amm_mint_b)?;
+
+    let pool_data = amm_pool.data.borrow();
+
+    let pool_mint_a = read_pubkey_at(&pool_data, layout.mint_a_offset)?;
+    let pool_mint_b = read_pubkey_at(&pool_data, layout.mint_b_offset)?;
+    require!(
+        pool_mint_a == amm_mint_a.key() && pool_mint_b == amm_mint_b.key(),
+        OracleError::InvalidAmmPool
+    );
+
+    let sqrt_price_x64 = read_u128_at(&pool_data, layout.sqrt_price_offset)?;
+    msg!("AMM sqrt_price_x64 was: {}", sqrt_price_x64);
+
+    let price = sqrt_price_to_decimal(sqrt_price_x64, mint_a.decimals, mint_b.decimals);
+    msg!("AMM decimal price was: {:?}", price);
+
+    Ok(price)
+}
+
+// Converts a Q64.64 sqrt_price into a human-readable spot price, rescaling
+// by 10^(decimals_a - decimals_b) since sqrt_price encodes the raw
+// token_b/token_a ratio in each mint's native (integer) units.
+fn sqrt_price_to_decimal(sqrt_price_x64: u128, decimals_a: u8, decimals_b: u8) -> Decimal {
+    let q64 = Decimal::from_u128(1u128 << 64).unwrap();
+    let sqrt_price = Decimal::from_u128(sqrt_price_x64).unwrap() / q64;
+    let mut price = sqrt_price * sqrt_price;
+
+    let decimals_diff = decimals_a as i32 - decimals_b as i32;
+    let decimals_adjustment = Decimal::from_u64(10u64.pow(decimals_diff.unsigned_abs())).unwrap();
+    if decimals_diff >= 0 {
+        price *= decimals_adjustment;
+    } else {
+        price /= decimals_adjustment;
+    }
+
+    price
+}
+
+// Ratio of a confidence/deviation amount to a price, expressed in basis points.
+fn confidence_ratio_bps(confidence: Decimal, price: Decimal) -> u64 {
+    if price.is_zero() {
+        return u64::MAX;
+    }
+    ((confidence / price) * Decimal::from(BPS_SCALE))
+        .round()
+        .to_u64()
+        .unwrap_or(u64::MAX)
+}
+
+// Relative deviation between two prices, expressed in basis points.
+fn deviation_bps(a: Decimal, b: Decimal) -> u64 {
+    let min = a.min(b);
+    if min.is_zero() {
+        return u64::MAX;
+    }
+    (((a - b).abs() / min) * Decimal::from(BPS_SCALE))
+        .round()
+        .to_u64()
+        .unwrap_or(u64::MAX)
+}
+
+// Exponentially-weighted moving average of the WAD-scale price, using a
+// configurable half-life so a single-slot spike can't dominate the smoothed
+// value consumers use for collateral valuation.
+fn update_ema(ema_price: u128, price: u128, last_update: u64, now: u64, half_life: u64) -> u128 {
+    if last_update == 0 {
+        return price;
+    }
+
+    let dt = now.saturating_sub(last_update);
+    if dt == 0 {
+        return ema_price;
+    }
+
+    let ema = Decimal::from_account(ema_price);
+    let p = Decimal::from_account(price);
+    let alpha = Decimal::from(dt) / Decimal::from(dt.saturating_add(half_life));
+
+    (ema + alpha * (p - ema)).to_account()
+}
+
 // A wad is a decimal number with 18 digits of precision
 const WAD: u128 = 1_000_000_000_000_000_000_u128;
 
@@ -248,3 +651,85 @@ impl NeptuneTraits for Decimal {
         Decimal::from_u64(1_000_000_000).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confidence_ratio_bps_computes_relative_width() {
+        let price = Decimal::from(100);
+        let confidence = Decimal::new(5, 1); // 0.5
+        assert_eq!(confidence_ratio_bps(confidence, price), 50); // 0.5% = 50 bps
+    }
+
+    #[test]
+    fn confidence_ratio_bps_rejects_zero_price() {
+        assert_eq!(confidence_ratio_bps(Decimal::ONE, Decimal::ZERO), u64::MAX);
+    }
+
+    #[test]
+    fn deviation_bps_is_symmetric_and_relative_to_the_smaller_price() {
+        let a = Decimal::from(100);
+        let b = Decimal::from(101);
+        assert_eq!(deviation_bps(a, b), deviation_bps(b, a));
+        assert_eq!(deviation_bps(a, b), 100); // 1/100 = 1% = 100 bps
+    }
+
+    #[test]
+    fn deviation_bps_is_zero_for_equal_prices() {
+        let a = Decimal::from(42);
+        assert_eq!(deviation_bps(a, a), 0);
+    }
+
+    #[test]
+    fn update_ema_seeds_on_first_update() {
+        let price = Decimal::from(100).to_account();
+        assert_eq!(update_ema(0, price, 0, 1_000, 60), price);
+    }
+
+    #[test]
+    fn update_ema_does_not_move_within_the_same_timestamp() {
+        let ema = Decimal::from(100).to_account();
+        let price = Decimal::from(200).to_account();
+        assert_eq!(update_ema(ema, price, 1_000, 1_000, 60), ema);
+    }
+
+    #[test]
+    fn update_ema_moves_halfway_to_the_new_price_after_one_half_life() {
+        let ema = Decimal::from(100).to_account();
+        let price = Decimal::from(200).to_account();
+        let half_life = 60;
+        let last_update = 1_000;
+        let updated = Decimal::from_account(update_ema(
+            ema,
+            price,
+            last_update,
+            last_update + half_life,
+            half_life,
+        ));
+        let expected = Decimal::from(150);
+        assert!((updated - expected).abs() < Decimal::new(1, 3)); // within 0.001
+    }
+
+    #[test]
+    fn sqrt_price_to_decimal_matches_equal_decimals() {
+        // sqrt_price for a 1:1 pool, both mints at the same decimals.
+        let sqrt_price_x64 = 1u128 << 64;
+        let price = sqrt_price_to_decimal(sqrt_price_x64, 6, 6);
+        assert_eq!(price, Decimal::ONE);
+    }
+
+    #[test]
+    fn sqrt_price_to_decimal_rescales_for_differing_decimals() {
+        // USDC (6 decimals) priced against SOL (9 decimals): a raw ratio of
+        // 1 should become 0.001 once rescaled by 10^(6 - 9).
+        let sqrt_price_x64 = 1u128 << 64;
+        let price = sqrt_price_to_decimal(sqrt_price_x64, 6, 9);
+        assert_eq!(price, Decimal::new(1, 3)); // 0.001
+
+        // And the inverse pair should scale the other way.
+        let price = sqrt_price_to_decimal(sqrt_price_x64, 9, 6);
+        assert_eq!(price, Decimal::from(1_000));
+    }
+}